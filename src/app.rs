@@ -134,6 +134,11 @@ impl Verso {
         &mut self.servo
     }
 
+    /// Check if the WebView has finished loading its page since the last call.
+    pub fn take_load_complete(&mut self) -> bool {
+        self.window.take_load_complete()
+    }
+
     /// Tell Verso to shut down Servo safely.
     pub fn shutdown(&mut self) {
         self.events.push(EmbedderEvent::Quit);
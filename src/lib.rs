@@ -13,12 +13,12 @@ pub mod errors;
 pub mod prefs;
 /// Utilities to access resource files
 pub mod resources;
+/// Utilities to write tests.
+pub mod test;
 /// Web view types to handle web browsing contexts.
 pub mod webview;
 /// Verso's window types to handle Winit's window.
 pub mod window;
-/// Utilities to write tests.
-// pub mod test;
 
 pub use app::{Status, Verso};
 pub use errors::{Error, Result};
@@ -1,16 +1,52 @@
-use std::path::PathBuf;
+use std::{fs, path::PathBuf};
 
-use servo::embedder_traits::resources::{self, Resource, ResourceReaderMethods};
+use servo::{
+    config::{basedir, opts},
+    embedder_traits::resources::{self, Resource, ResourceReaderMethods},
+};
 
 struct ResourceReader;
 
-/// Initialize resource files. We currently read from `resources` directory only.
+/// Initialize resource files. We read from the bundled `resources` directory.
+///
+/// A file with the same name in the `resources` directory under the config directory (the same
+/// one `prefs.json` is read from) takes precedence over the bundled one, so it can be replaced
+/// without rebuilding Verso. Preferences are excluded since `prefs.json` in the config directory
+/// already overlays them. These files are trusted like the bundled ones (user agent stylesheets,
+/// error pages...), so the directory should only be writable by the user.
 pub fn init() {
     resources::set(Box::new(ResourceReader));
 }
 
+/// Directory of resource files taking precedence over the bundled ones.
+///
+/// This is resolved on every use, since options are parsed after resources are initialized.
+fn overrides_dir() -> Option<PathBuf> {
+    opts::get()
+        .config_dir
+        .clone()
+        .or_else(basedir::default_config_dir)
+        .map(|path| path.join("resources"))
+        .filter(|path| path.is_dir())
+}
+
 impl ResourceReaderMethods for ResourceReader {
     fn read(&self, file: Resource) -> Vec<u8> {
+        // Preferences are read before options are parsed, and are overlaid by `prefs.json`.
+        let overrides = match file {
+            Resource::Preferences => None,
+            _ => overrides_dir(),
+        };
+        if let Some(dir) = overrides {
+            let path = dir.join(file.filename());
+            if path.is_file() {
+                match fs::read(&path) {
+                    Ok(bytes) => return bytes,
+                    Err(e) => log::warn!("Failed to read resource override {path:?}: {e}"),
+                }
+            }
+        }
+
         match file {
             Resource::Preferences => &include_bytes!("../resources/prefs.json")[..],
             Resource::BluetoothBlocklist => &include_bytes!("../resources/gatt_blocklist.txt")[..],
@@ -37,6 +73,6 @@ impl ResourceReaderMethods for ResourceReader {
     }
 
     fn sandbox_access_files_dirs(&self) -> Vec<PathBuf> {
-        vec![]
+        overrides_dir().into_iter().collect()
     }
 }
@@ -32,7 +32,10 @@ macro_rules! verso_test {
                 $crate::__verso_test_internal_collect_test!($test)
             ),*];
 
-            $crate::test::__private::run(TESTS, ());
+            let code = $crate::test::__private::run(TESTS, ());
+            if code != 0 {
+                std::process::exit(code);
+            }
             Ok(())
         }
     };
@@ -73,8 +76,8 @@ pub mod __private {
         code: i32,
     }
 
-    /// Run a set of tests using a Winit context.
-    pub fn run(tests: &'static [VersoBasedTest], _ctx: Context) {
+    /// Run a set of tests using a Winit context and return the exit code.
+    pub fn run(tests: &'static [VersoBasedTest], _ctx: Context) -> i32 {
         // Create a new event loop and obtain a window target.
         let event_loop = EventLoop::new().expect("Failed to build event loop");
         let window = WindowBuilder::new()
@@ -93,16 +96,21 @@ pub mod __private {
         };
 
         // Run the tests.
+        let state_ref = &mut state;
         event_loop
-            .run(move |event, elwt| match verso.run(event, elwt) {
-                Status::LoadComplete => {
-                    run_internal(tests, &mut state, elwt);
+            .run(move |event, elwt| {
+                let status = verso.run(event);
+                if verso.take_load_complete() {
+                    run_internal(tests, state_ref, elwt);
                     verso.shutdown();
                 }
-                Status::Shutdown => elwt.exit(),
-                _ => (),
+                if let Status::Shutdown = status {
+                    elwt.exit();
+                }
             })
             .expect("Event loop failed to run");
+
+        state.code
     }
 
     /// Run a set of tests using a Winit context.
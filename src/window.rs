@@ -6,9 +6,11 @@ use servo::{
     compositing::windowing::{
         AnimationState, EmbedderCoordinates, EmbedderEvent, MouseWindowEvent, WindowMethods,
     },
+    config::pref,
     embedder_traits::{Cursor, EmbedderMsg},
     euclid::{Point2D, Scale, Size2D},
     gl,
+    keyboard_types::{CompositionEvent, CompositionState},
     script_traits::{TouchEventType, TraversalDirection, WheelDelta, WheelMode},
     style_traits::DevicePixel,
     url::ServoUrl,
@@ -21,8 +23,9 @@ use servo::{
 };
 use surfman::{Connection, GLApi, SurfaceType};
 use winit::{
-    dpi::PhysicalPosition,
-    event::{ElementState, TouchPhase, WindowEvent},
+    dpi::{PhysicalPosition, PhysicalSize},
+    event::{ElementState, Ime, MouseScrollDelta, TouchPhase, WindowEvent},
+    keyboard::ModifiersState,
     window::{CursorIcon, Window as WinitWindow},
 };
 
@@ -31,6 +34,9 @@ use crate::{
     Status,
 };
 
+/// Height of the panel in device pixels. The web view is placed right below it.
+const PANEL_HEIGHT: i32 = 76;
+
 /// A Verso window is a Winit window containing several web views.
 pub struct Window {
     /// Access to Winit window with webrender context.
@@ -43,6 +49,12 @@ pub struct Window {
     webrender_gl: Rc<dyn gl::Gl>,
     /// The mouse physical position in the web view.
     mouse_position: Cell<PhysicalPosition<f64>>,
+    /// Modifier keys currently held down.
+    modifiers_state: Cell<ModifiersState>,
+    /// Whether an IME composition is in progress.
+    composing: Cell<bool>,
+    /// Whether the WebView has finished loading since it was last checked.
+    load_complete: bool,
 }
 
 impl Window {
@@ -77,6 +89,9 @@ impl Window {
             webview: None,
             webrender_gl,
             mouse_position: Cell::new(PhysicalPosition::default()),
+            modifiers_state: Cell::new(ModifiersState::default()),
+            composing: Cell::new(false),
+            load_complete: false,
         }
     }
 
@@ -148,18 +163,11 @@ impl Window {
                 events.push(EmbedderEvent::Zoom(1.0 + *delta as f32));
             }
             WindowEvent::MouseWheel { delta, phase, .. } => {
-                // FIXME: Pixels per line, should be configurable (from browser setting?) and vary by zoom level.
-                const LINE_HEIGHT: f32 = 38.0;
-
-                let (mut x, mut y, mode) = match delta {
-                    winit::event::MouseScrollDelta::LineDelta(x, y) => {
-                        (*x as f64, (*y * LINE_HEIGHT) as f64, WheelMode::DeltaLine)
-                    }
-                    winit::event::MouseScrollDelta::PixelDelta(position) => {
-                        let position = position.to_logical::<f64>(self.window.scale_factor());
-                        (position.x, position.y, WheelMode::DeltaPixel)
-                    }
-                };
+                let (mut x, mut y, mode) = wheel_delta(
+                    delta,
+                    self.window.scale_factor(),
+                    self.modifiers_state.get().shift_key(),
+                );
 
                 // Wheel Event
                 events.push(EmbedderEvent::Wheel(
@@ -194,6 +202,48 @@ impl Window {
                     phase,
                 ));
             }
+            WindowEvent::Ime(ime) => {
+                let composition = |state, data: &str| {
+                    EmbedderEvent::IMEComposition(CompositionEvent {
+                        state,
+                        data: data.to_owned(),
+                    })
+                };
+                match ime {
+                    // Winit reports this when IME is allowed, not when a composition starts.
+                    Ime::Enabled => {}
+                    // An empty preedit clears the composition, which we treat as a cancel. Winit
+                    // also sends one right before a commit, so a commit may arrive while not
+                    // composing.
+                    Ime::Preedit(text, _) if text.is_empty() => {
+                        if self.composing.replace(false) {
+                            events.push(composition(CompositionState::End, ""));
+                        }
+                    }
+                    Ime::Preedit(text, _) => {
+                        if !self.composing.replace(true) {
+                            events.push(composition(CompositionState::Start, ""));
+                        }
+                        events.push(composition(CompositionState::Update, text.as_str()));
+                    }
+                    Ime::Commit(text) => {
+                        if !self.composing.replace(false) {
+                            events.push(composition(CompositionState::Start, ""));
+                        }
+                        events.push(composition(CompositionState::End, text.as_str()));
+                    }
+                    // This follows our own `set_ime_allowed(false)`, so it isn't reported as
+                    // `IMEDismissed`, which would blur the focused input.
+                    Ime::Disabled => {
+                        if self.composing.replace(false) {
+                            events.push(composition(CompositionState::End, ""));
+                        }
+                    }
+                }
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.modifiers_state.set(modifiers.state());
+            }
             WindowEvent::CloseRequested => {
                 events.push(EmbedderEvent::Quit);
             }
@@ -220,9 +270,13 @@ impl Window {
                         }
                         EmbedderMsg::LoadComplete => {
                             need_present = true;
-                            let demo_url = ServoUrl::parse("https://demo.versotile.org").unwrap();
-                            let demo_id = TopLevelBrowsingContextId::new();
-                            events.push(EmbedderEvent::NewWebView(demo_url, demo_id));
+                            let homepage = pref!(shell.homepage);
+                            let url = ServoUrl::parse(&homepage).unwrap_or_else(|e| {
+                                log::warn!("Verso Panel failed to parse homepage {homepage}: {e}");
+                                ServoUrl::parse("https://demo.versotile.org").unwrap()
+                            });
+                            let id = TopLevelBrowsingContextId::new();
+                            events.push(EmbedderEvent::NewWebView(url, id));
                         }
                         EmbedderMsg::AllowNavigationRequest(id, _url) => {
                             // The panel shouldn't navigate to other pages.
@@ -245,6 +299,22 @@ impl Window {
                         | EmbedderMsg::ChangePageTitle(..) => {
                             log::trace!("Verso Panel ignores this message: {m:?}")
                         }
+                        EmbedderMsg::GetClipboardContents(sender) => {
+                            // Script blocks on this reply, so answer even without a clipboard.
+                            if let Err(e) = sender.send(String::new()) {
+                                log::warn!("Verso Panel failed to send clipboard contents: {e}");
+                            }
+                        }
+                        EmbedderMsg::SetClipboardContents(_text) => {
+                            log::trace!("Verso Panel ignores clipboard contents for now");
+                        }
+                        EmbedderMsg::ShowIME(_kind, _text, _multiline, rect) => {
+                            // Despite its `DeviceIntRect` type, the rect is in CSS pixels.
+                            self.show_ime(rect, 0);
+                        }
+                        EmbedderMsg::HideIME => {
+                            self.window.set_ime_allowed(false);
+                        }
                         EmbedderMsg::Prompt(definition, _origin) => match definition {
                             servo::embedder_traits::PromptDefinition::Input(
                                 msg,
@@ -299,6 +369,7 @@ impl Window {
                         }
                         EmbedderMsg::LoadComplete => {
                             need_present = true;
+                            self.load_complete = true;
                         }
                         EmbedderMsg::WebViewOpened(w) => {
                             let webview = WebView::new(w);
@@ -307,10 +378,18 @@ impl Window {
                             let size = self.window.inner_size();
                             let size = Size2D::new(size.width as i32, size.height as i32);
                             let mut rect = DeviceIntRect::from_size(size).to_f32();
-                            rect.min.y = rect.max.y.min(76.);
+                            rect.min.y = rect.max.y.min(PANEL_HEIGHT as f32);
                             events.push(EmbedderEvent::FocusWebView(w));
                             events.push(EmbedderEvent::MoveResizeWebView(w, rect));
                         }
+                        EmbedderMsg::AllowOpeningWebView(sender) => {
+                            // TODO open popups once a window can hold more than one web view
+                            if let Err(e) = sender.send(false) {
+                                log::warn!(
+                                    "Verso WebView failed to respond to opening web view: {e}"
+                                );
+                            }
+                        }
                         EmbedderMsg::AllowNavigationRequest(id, _url) => {
                             // TODO should provide a API for users to check url
                             events.push(EmbedderEvent::AllowNavigationResponse(id, true));
@@ -321,6 +400,27 @@ impl Window {
                         EmbedderMsg::WebViewFocused(w) => {
                             events.push(EmbedderEvent::ShowWebView(w, false));
                         }
+                        EmbedderMsg::ShowIME(_kind, _text, _multiline, rect) => {
+                            // Despite its `DeviceIntRect` type, the rect is in CSS pixels. The web
+                            // view sits below the panel.
+                            self.show_ime(rect, PANEL_HEIGHT);
+                        }
+                        EmbedderMsg::HideIME => {
+                            self.window.set_ime_allowed(false);
+                        }
+                        EmbedderMsg::MoveTo(..) | EmbedderMsg::ResizeTo(..) => {
+                            // Pages can't move or resize a window that script didn't open.
+                            log::trace!("Verso WebView ignores this message: {m:?}")
+                        }
+                        EmbedderMsg::GetClipboardContents(sender) => {
+                            // Script blocks on this reply, so answer even without a clipboard.
+                            if let Err(e) = sender.send(String::new()) {
+                                log::warn!("Verso WebView failed to send clipboard contents: {e}");
+                            }
+                        }
+                        EmbedderMsg::SetClipboardContents(_text) => {
+                            log::trace!("Verso WebView ignores clipboard contents for now");
+                        }
                         e => {
                             log::warn!(
                                 "Verso WebView isn't supporting this message yet: {e:?}"
@@ -400,6 +500,11 @@ impl Window {
         }
     }
 
+    /// Check if the WebView has finished loading since the last call, and reset the flag.
+    pub fn take_load_complete(&mut self) -> bool {
+        std::mem::take(&mut self.load_complete)
+    }
+
     /// Queues a Winit `WindowEvent::RedrawRequested` event to be emitted that aligns with the windowing system drawing loop.
     pub fn request_redraw(&self) {
         self.window.request_redraw()
@@ -420,11 +525,30 @@ impl Window {
 
         if let Some(w) = &self.webview {
             let mut rect = DeviceIntRect::from_size(size).to_f32();
-            rect.min.y = rect.max.y.min(76.);
+            rect.min.y = rect.max.y.min(PANEL_HEIGHT as f32);
             events.push(EmbedderEvent::MoveResizeWebView(w.id(), rect));
         }
     }
 
+    /// Allow IME input and place the candidate window over the focused text input.
+    ///
+    /// `rect` is the input's box in CSS pixels, relative to a web view placed `offset` device
+    /// pixels below the top of the window.
+    fn show_ime(&self, rect: DeviceIntRect, offset: i32) {
+        // FIXME: This should be the web view's device pixel ratio, which also includes page zoom.
+        let scale = self.window.scale_factor();
+        let position = PhysicalPosition::new(
+            (rect.min.x as f64 * scale) as i32,
+            (rect.min.y as f64 * scale) as i32 + offset,
+        );
+        let size = PhysicalSize::new(
+            (rect.width() as f64 * scale) as u32,
+            (rect.height() as f64 * scale) as u32,
+        );
+        self.window.set_ime_allowed(true);
+        self.window.set_ime_cursor_area(position, size);
+    }
+
     /// Set cursor icon of the window.
     pub fn set_cursor_icon(&self, cursor: Cursor) {
         let winit_cursor = match cursor {
@@ -468,6 +592,34 @@ impl Window {
     }
 }
 
+/// Convert a Winit wheel delta to the scroll amount sent to Servo.
+///
+/// Line deltas are scaled to pixels and pixel deltas are converted to logical pixels. Holding
+/// shift turns a vertical wheel into a horizontal one.
+fn wheel_delta(delta: &MouseScrollDelta, scale_factor: f64, shift: bool) -> (f64, f64, WheelMode) {
+    // FIXME: Pixels per line, should be configurable (from browser setting?) and vary by zoom level.
+    const LINE_HEIGHT: f32 = 38.0;
+
+    let (mut x, mut y, mode) = match delta {
+        MouseScrollDelta::LineDelta(x, y) => (
+            (*x * LINE_HEIGHT) as f64,
+            (*y * LINE_HEIGHT) as f64,
+            WheelMode::DeltaLine,
+        ),
+        MouseScrollDelta::PixelDelta(position) => {
+            let position = position.to_logical::<f64>(scale_factor);
+            (position.x, position.y, WheelMode::DeltaPixel)
+        }
+    };
+
+    // Trackpads already report a horizontal delta, so only swap a purely vertical one.
+    if shift && x == 0.0 {
+        (x, y) = (y, 0.0);
+    }
+
+    (x, y, mode)
+}
+
 /// A Winit window with webrender rendering context.
 pub struct GLWindow {
     /// Access to webrender rendering context
@@ -528,3 +680,42 @@ impl Deref for Window {
         &self.gl_window
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn line_delta_is_scaled_to_pixels() {
+        let delta = MouseScrollDelta::LineDelta(0.0, -2.0);
+        let (x, y, mode) = wheel_delta(&delta, 2.0, false);
+        assert_eq!((x, y), (0.0, -76.0));
+        assert!(matches!(mode, WheelMode::DeltaLine));
+    }
+
+    #[test]
+    fn line_delta_with_shift_scrolls_horizontally() {
+        let delta = MouseScrollDelta::LineDelta(0.0, -2.0);
+        let (x, y, mode) = wheel_delta(&delta, 1.0, true);
+        assert_eq!((x, y), (-76.0, 0.0));
+        assert!(matches!(mode, WheelMode::DeltaLine));
+    }
+
+    #[test]
+    fn pixel_delta_is_converted_to_logical_pixels() {
+        let delta = MouseScrollDelta::PixelDelta(PhysicalPosition::new(10.0, 40.0));
+        let (x, y, mode) = wheel_delta(&delta, 1.0, false);
+        assert_eq!((x, y), (10.0, 40.0));
+        assert!(matches!(mode, WheelMode::DeltaPixel));
+
+        let (x, y, _) = wheel_delta(&delta, 2.0, false);
+        assert_eq!((x, y), (5.0, 20.0));
+    }
+
+    #[test]
+    fn shift_keeps_trackpad_horizontal_delta() {
+        let delta = MouseScrollDelta::PixelDelta(PhysicalPosition::new(6.0, 30.0));
+        let (x, y, _) = wheel_delta(&delta, 1.0, true);
+        assert_eq!((x, y), (6.0, 30.0));
+    }
+}